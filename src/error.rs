@@ -1,26 +1,37 @@
+#[cfg(unix)]
 use nix::errno::Errno;
 use std::fmt;
 use std::io;
+use std::path::PathBuf;
 
 /// A struct that encapsulates an error returned by a single
 /// worker thread.
 #[derive(Debug)]
 pub struct WorkerError {
     thread_id: usize,
+    path: PathBuf,
     error: ErrorKind,
 }
 
 impl WorkerError {
-    pub fn new(thread_id: usize, error: ErrorKind) -> Self {
-        WorkerError { thread_id, error }
+    pub fn new(thread_id: usize, path: PathBuf, error: ErrorKind) -> Self {
+        WorkerError {
+            thread_id,
+            path,
+            error,
+        }
     }
 }
 
 /// Custom `Error` kinds for `disco`.
 #[derive(Debug)]
 pub enum ErrorKind {
+    #[cfg(unix)]
     UnixError(Errno),
+    #[cfg(windows)]
+    WindowsError(u32),
     IOError(io::Error),
+    GetRandomError(getrandom::Error),
     WorkerErrors(Vec<WorkerError>),
 }
 
@@ -29,10 +40,18 @@ pub type Result<T> = core::result::Result<T, ErrorKind>;
 
 impl fmt::Display for WorkerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "thread={}, err={}", self.thread_id, self.error)
+        write!(
+            f,
+            "thread={}, path={}, err={}",
+            self.thread_id,
+            self.path.display(),
+            self.error
+        )
     }
 }
 
+impl std::error::Error for ErrorKind {}
+
 impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -40,10 +59,19 @@ impl fmt::Display for ErrorKind {
                 write!(f, "IOError: ")?;
                 e.fmt(f)
             }
+            ErrorKind::GetRandomError(e) => {
+                write!(f, "GetRandomError: ")?;
+                e.fmt(f)
+            }
+            #[cfg(unix)]
             ErrorKind::UnixError(e) => {
                 write!(f, "UnixError: ")?;
                 e.fmt(f)
             }
+            #[cfg(windows)]
+            ErrorKind::WindowsError(code) => {
+                write!(f, "WindowsError: code {}", code)
+            }
             ErrorKind::WorkerErrors(errs) => {
                 write!(f, "(Worker errors)")?;
                 for e in errs {
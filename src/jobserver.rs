@@ -0,0 +1,225 @@
+//! Integration with the GNU Make jobserver protocol.
+//!
+//! When `disco` runs as part of a `make -jN` recipe, Make may advertise a
+//! jobserver through `MAKEFLAGS` so that child processes can cooperate on a
+//! shared pool of job slots instead of each spawning as much parallelism as
+//! they please. This module implements just enough of the protocol to let
+//! `disco` bound the number of live writer threads it spawns to the number
+//! of slots Make has actually granted it.
+
+use crate::core::default_sleep_time;
+use crate::error::{ErrorKind, Result};
+
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A connection to a GNU Make jobserver.
+///
+/// Every process taking part in the protocol owns one implicit slot for
+/// free; each additional slot must be acquired by reading a single byte
+/// from `read`, and released by writing a single byte back to `write`.
+pub struct JobServer {
+    read: File,
+    write: File,
+
+    /// Serializes the body of `acquire`: toggling `read`'s O_NONBLOCK flag
+    /// and reading from it aren't atomic with respect to each other, and
+    /// the flag lives on the underlying file description rather than the
+    /// fd, so two threads calling `acquire` concurrently on a cloned
+    /// `Arc<JobServer>` could otherwise flip each other's nonblocking mode
+    /// mid-read. For a `fifo:` auth, `connect` opens `read` and `write`
+    /// as independent descriptions so that `release` is unaffected by the
+    /// toggle, but every `acquire` still shares the one `read`, so both
+    /// auth forms serialize here.
+    acquire_lock: Mutex<()>,
+}
+
+impl JobServer {
+    /// Parse `--jobserver-auth` (or the older `--jobserver-fds`) out of the
+    /// `MAKEFLAGS` environment variable and connect to the jobserver it
+    /// names. Returns `Ok(None)` if `disco` wasn't launched underneath one,
+    /// which is the common case of running it directly from a shell.
+    pub fn from_env() -> Result<Option<Self>> {
+        let makeflags = match env::var("MAKEFLAGS") {
+            Ok(s) => s,
+            Err(_) => return Ok(None),
+        };
+
+        match parse_auth(&makeflags) {
+            Some(auth) => Self::connect(auth).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn connect(auth: &str) -> Result<Self> {
+        if let Some(path) = auth.strip_prefix("fifo:") {
+            // On Linux a FIFO may be opened read-write by a single
+            // descriptor without blocking on a peer, so we use that trick
+            // for both ends of the connection — but we open the path twice
+            // rather than `try_clone`-ing one descriptor, so that `read`
+            // and `write` get independent file descriptions. Descriptors
+            // sharing a file description also share file-status flags, so
+            // `acquire`'s O_NONBLOCK toggle on `read` would otherwise make
+            // a concurrent `release` write nonblocking too, and a `write`
+            // that then hit `WouldBlock` would silently drop its token.
+            let open = || -> io::Result<File> { OpenOptions::new().read(true).write(true).open(path) };
+            let read = open().map_err(ErrorKind::IOError)?;
+            let write = open().map_err(ErrorKind::IOError)?;
+            return Ok(JobServer {
+                read,
+                write,
+                acquire_lock: Mutex::new(()),
+            });
+        }
+
+        let (r, w) = auth
+            .split_once(',')
+            .ok_or_else(|| ErrorKind::IOError(invalid_auth(auth)))?;
+        let r: RawFd = r.parse().map_err(|_| ErrorKind::IOError(invalid_auth(auth)))?;
+        let w: RawFd = w.parse().map_err(|_| ErrorKind::IOError(invalid_auth(auth)))?;
+
+        // Safety: `make` hands these descriptors down to us pre-opened for
+        // the lifetime of this process; we neither close nor dup them
+        // anywhere else.
+        let read = unsafe { File::from_raw_fd(r) };
+        let write = unsafe { File::from_raw_fd(w) };
+        Ok(JobServer {
+            read,
+            write,
+            acquire_lock: Mutex::new(()),
+        })
+    }
+
+    /// Block until a job slot is available, returning a guard that releases
+    /// it on drop. Polls `running` between attempts so that a shutdown
+    /// request isn't stuck behind a jobserver that never frees a slot;
+    /// returns `Ok(None)` if `running` went false before a slot was
+    /// acquired.
+    ///
+    /// Takes `self` by `Arc` so the returned guard can outlive the caller's
+    /// stack frame: callers acquire from inside the worker thread that will
+    /// hold the slot, not from the thread that spawns it.
+    pub fn acquire(self: Arc<Self>, running: &AtomicBool) -> Result<Option<Acquired>> {
+        // Hold this for the whole toggle-read-untoggle cycle below: `read`'s
+        // O_NONBLOCK flag is shared by every clone of this `Arc`, so letting
+        // two threads interleave their toggles would let one thread's
+        // "done, make it blocking again" stomp on another thread's
+        // in-progress nonblocking read loop.
+        let _guard = self.acquire_lock.lock().unwrap();
+
+        set_nonblocking(self.read.as_raw_fd(), true).map_err(ErrorKind::IOError)?;
+
+        let result = loop {
+            if !running.load(Ordering::SeqCst) {
+                break Ok(None);
+            }
+
+            let mut byte = [0u8; 1];
+            match (&self.read).read(&mut byte) {
+                Ok(0) => {
+                    break Err(ErrorKind::IOError(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "jobserver pipe closed unexpectedly",
+                    )))
+                }
+                Ok(_) => break Ok(Some(Acquired { server: self.clone() })),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(default_sleep_time());
+                    continue;
+                }
+                Err(e) => break Err(ErrorKind::IOError(e)),
+            }
+        };
+
+        set_nonblocking(self.read.as_raw_fd(), false).map_err(ErrorKind::IOError)?;
+        result
+    }
+
+    fn release(&self) {
+        // Best-effort: if the other end has gone away there's no one left
+        // to hand the slot back to.
+        let _ = (&self.write).write_all(&[b'+']);
+    }
+}
+
+/// Guard representing one acquired jobserver slot. Writes the slot's byte
+/// back to the jobserver when dropped.
+pub struct Acquired {
+    server: Arc<JobServer>,
+}
+
+impl Drop for Acquired {
+    fn drop(&mut self) {
+        self.server.release();
+    }
+}
+
+/// Parse `--jobserver-auth`/`--jobserver-fds` out of a `MAKEFLAGS` value.
+fn parse_auth(makeflags: &str) -> Option<&str> {
+    makeflags.split_whitespace().find_map(|arg| {
+        arg.strip_prefix("--jobserver-auth=")
+            .or_else(|| arg.strip_prefix("--jobserver-fds="))
+    })
+}
+
+fn set_nonblocking(fd: RawFd, nonblocking: bool) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL, 0);
+        if flags < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let flags = if nonblocking {
+            flags | libc::O_NONBLOCK
+        } else {
+            flags & !libc::O_NONBLOCK
+        };
+        if libc::fcntl(fd, libc::F_SETFL, flags) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn invalid_auth(auth: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("malformed --jobserver-auth value: {}", auth),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_auth_modern() {
+        assert_eq!(parse_auth("-j --jobserver-auth=3,4 --other"), Some("3,4"));
+    }
+
+    #[test]
+    fn test_parse_auth_legacy_fds() {
+        assert_eq!(parse_auth("--jobserver-fds=3,4 -j"), Some("3,4"));
+    }
+
+    #[test]
+    fn test_parse_auth_fifo() {
+        assert_eq!(parse_auth("--jobserver-auth=fifo:/tmp/x"), Some("fifo:/tmp/x"));
+    }
+
+    #[test]
+    fn test_parse_auth_absent() {
+        assert_eq!(parse_auth("-j4"), None);
+    }
+
+    #[test]
+    fn test_connect_rejects_malformed_auth() {
+        assert!(JobServer::connect("not-fds-and-no-comma-either").is_err());
+        assert!(JobServer::connect("abc,4").is_err());
+    }
+}
@@ -4,28 +4,46 @@
 
 mod core;
 mod error;
+#[cfg(unix)]
+mod jobserver;
 mod rng;
 mod stream;
 mod workers;
 use crate::error::Result;
+use crate::rng::ReseedPolicy;
+use crate::workers::OutputSpec;
 
 extern crate libc;
 extern crate test;
 
 use clap::{arg, command, Command};
-use std::path::Path;
+use std::time::Duration;
 
 fn create_argparser() -> Command<'static> {
     command!()
         .arg(
-            arg!(-t --threads "The number of worker threads to spawn")
+            arg!(-t --threads "The number of worker threads to spawn, per output")
                 .default_value("1")
                 .validator(|s| s.parse::<usize>())
                 .required(false),
         )
         .arg(
-            arg!(-o --output "The file to write to; defaults to /dev/stdout")
+            arg!(-o --output "The file to write to; may be given more than once, and each \
+                  may carry a @BYTES_PER_SEC throughput cap, e.g. /tmp/fifo@1048576")
                 .default_value("/dev/stdout")
+                .validator(|s| s.parse::<OutputSpec>())
+                .multiple_occurrences(true)
+                .required(false),
+        )
+        .arg(
+            arg!(--"reseed-bytes" "Number of output bytes a worker emits before mixing in fresh OS entropy")
+                .default_value("1048576")
+                .validator(|s| s.parse::<u64>())
+                .required(false),
+        )
+        .arg(
+            arg!(--"reseed-interval" "Seconds between mixing in fresh OS entropy, regardless of --reseed-bytes")
+                .validator(|s| s.parse::<u64>())
                 .required(false),
         )
 }
@@ -33,12 +51,23 @@ fn create_argparser() -> Command<'static> {
 fn main() -> Result<()> {
     let matches = create_argparser().get_matches();
 
-    let path = matches.value_of("output").expect("required");
-    let path = Path::new(&path);
+    let outputs: Vec<OutputSpec> = matches.values_of_t("output").expect("required");
     let n_threads = matches.value_of_t("threads").expect("required");
 
-    eprintln!("Writing stream to {}", path.display());
-    stream::run_workers(path, n_threads)
+    let reseed_bytes = matches.value_of_t("reseed-bytes").expect("required");
+    let reseed_interval = matches
+        .value_of_t::<u64>("reseed-interval")
+        .ok()
+        .map(Duration::from_secs);
+    let reseed_policy = ReseedPolicy {
+        reseed_bytes,
+        reseed_interval,
+    };
+
+    for output in &outputs {
+        eprintln!("Writing stream to {}", output.path.display());
+    }
+    stream::run_workers(&outputs, n_threads, reseed_policy)
 }
 
 #[cfg(test)]
@@ -50,6 +79,7 @@ mod tests {
     use std::error::Error;
     use std::fs::{self, File};
     use std::io::Read;
+    use std::path::{Path, PathBuf};
     use std::result::Result;
     use std::sync::atomic::Ordering;
     use tempfile::NamedTempFile;
@@ -74,7 +104,11 @@ mod tests {
         let path = path.as_path();
         create_fifo(&path, None)?;
 
-        let pool = stream::start_workers(&path, n_threads)?;
+        let outputs = [OutputSpec {
+            path: path.to_owned(),
+            rate_limit: None,
+        }];
+        let pool = stream::start_workers(&outputs, n_threads, ReseedPolicy::default())?;
         let mut file = File::open(&path)?;
         let mut buf = [0u8; BENCH_BUFSIZE];
 
@@ -5,11 +5,16 @@
 use crate::error::{ErrorKind, Result};
 use chacha20::cipher::{KeyIvInit, StreamCipher};
 use chacha20::ChaCha20;
+use std::time::{Duration, Instant};
 
 const BUFFER_LEN: usize = 1024;
 const KEY_LEN: usize = 32;
 const OUTPUT_LEN: usize = BUFFER_LEN - KEY_LEN;
 
+/// Default number of output bytes a `CryptoRng` emits before it mixes in
+/// fresh OS entropy; see [`ReseedPolicy`].
+const DEFAULT_RESEED_BYTES: u64 = 1 << 20;
+
 type Buffer = [u8; BUFFER_LEN];
 type Key = [u8; KEY_LEN];
 type Output = [u8; OUTPUT_LEN];
@@ -19,16 +24,47 @@ unsafe fn slice_to_array<const N: usize>(slice: &[u8]) -> &[u8; N] {
     &*(slice.as_ptr() as *const [u8; N])
 }
 
+/// Controls how often a `CryptoRng` mixes fresh entropy from the OS back
+/// into its key, for prediction resistance: even if a key is ever
+/// compromised, future output recovers once the next reseed happens.
+#[derive(Debug, Clone, Copy)]
+pub struct ReseedPolicy {
+    /// Reseed after this many output bytes have been emitted.
+    pub reseed_bytes: u64,
+    /// Reseed after this much wall-clock time has elapsed, regardless of
+    /// how many bytes have been emitted. `None` disables the time-based
+    /// trigger.
+    pub reseed_interval: Option<Duration>,
+}
+
+impl Default for ReseedPolicy {
+    fn default() -> Self {
+        ReseedPolicy {
+            reseed_bytes: DEFAULT_RESEED_BYTES,
+            reseed_interval: None,
+        }
+    }
+}
+
 pub struct CryptoRng {
     buffer: Buffer,
     nonce: [u8; 12],
+    policy: ReseedPolicy,
+    bytes_since_reseed: u64,
+    last_reseed: Instant,
 }
 
 impl CryptoRng {
-    pub fn from_entropy() -> Result<Self> {
+    pub fn from_entropy_with_policy(policy: ReseedPolicy) -> Result<Self> {
         let buffer = [0u8; BUFFER_LEN];
         let nonce = [0u8; 12];
-        let mut crng = CryptoRng { buffer, nonce };
+        let mut crng = CryptoRng {
+            buffer,
+            nonce,
+            policy,
+            bytes_since_reseed: 0,
+            last_reseed: Instant::now(),
+        };
 
         // Initialize the key for the CryptoRng using the operating system's
         // random stream.
@@ -56,10 +92,43 @@ impl CryptoRng {
         unsafe { slice_to_array::<OUTPUT_LEN>(self.output_slice()) }
     }
 
+    fn should_reseed(&self) -> bool {
+        self.bytes_since_reseed >= self.policy.reseed_bytes
+            || self
+                .policy
+                .reseed_interval
+                .map_or(false, |interval| self.last_reseed.elapsed() >= interval)
+    }
+
+    /// Mix fresh OS entropy into the key by XOR-ing it in, rather than
+    /// replacing the key outright, so that a transiently weak OS source
+    /// can never reduce the key's entropy below what it already had.
+    fn reseed(&mut self) {
+        let mut fresh = [0u8; KEY_LEN];
+        if getrandom::getrandom(&mut fresh).is_err() {
+            // Best-effort: if the OS entropy source is transiently
+            // unavailable, keep running on the existing key rather than
+            // interrupting the stream. We'll try again next time
+            // should_reseed() fires.
+            return;
+        }
+
+        for (k, f) in self.key_slice().iter_mut().zip(fresh.iter()) {
+            *k ^= f;
+        }
+        self.bytes_since_reseed = 0;
+        self.last_reseed = Instant::now();
+    }
+
     pub fn regenerate(&mut self) -> &Output {
+        if self.should_reseed() {
+            self.reseed();
+        }
+
         let nonce = self.nonce;
         let mut cipher = ChaCha20::new(self.key().into(), &nonce.into());
         cipher.apply_keystream(&mut self.buffer);
+        self.bytes_since_reseed += OUTPUT_LEN as u64;
         self.output()
     }
 }
@@ -68,11 +137,11 @@ impl CryptoRng {
 mod test {
     use super::*;
 
-    /// Check that CryptoRng::from_entropy() creates a key that is filled
-    /// with random data.
+    /// Check that CryptoRng::from_entropy_with_policy() creates a key that
+    /// is filled with random data.
     #[test]
     fn test_from_entropy() {
-        let mut crng = CryptoRng::from_entropy().unwrap();
+        let mut crng = CryptoRng::from_entropy_with_policy(ReseedPolicy::default()).unwrap();
         let key = crng.key().clone();
         let zeros = [0u8; KEY_LEN];
 
@@ -84,7 +153,7 @@ mod test {
     /// output of the CryptoRng.
     #[test]
     fn test_fke() {
-        let mut crng = CryptoRng::from_entropy().unwrap();
+        let mut crng = CryptoRng::from_entropy_with_policy(ReseedPolicy::default()).unwrap();
         let key1 = crng.key().clone();
 
         crng.regenerate();
@@ -92,4 +161,33 @@ mod test {
 
         assert!(key1 != key2);
     }
+
+    /// Check that forcing a reseed (by setting `reseed_bytes` to 0) changes
+    /// the key in a way the prior keystream alone couldn't predict: the new
+    /// key must differ both from the pre-reseed key and from what plain
+    /// fast key erasure would have produced on its own.
+    #[test]
+    fn test_reseed_changes_key() {
+        let policy = ReseedPolicy {
+            reseed_bytes: 0,
+            reseed_interval: None,
+        };
+
+        let mut reseeding = CryptoRng::from_entropy_with_policy(policy).unwrap();
+        let mut erasure_only = CryptoRng::from_entropy_with_policy(ReseedPolicy {
+            reseed_bytes: u64::MAX,
+            reseed_interval: None,
+        })
+        .unwrap();
+
+        // Give both RNGs the same starting key.
+        reseeding.buffer[..KEY_LEN].copy_from_slice(&erasure_only.buffer[..KEY_LEN]);
+        let key_before = reseeding.key().clone();
+
+        reseeding.regenerate();
+        erasure_only.regenerate();
+
+        assert!(*reseeding.key() != key_before);
+        assert!(reseeding.key() != erasure_only.key());
+    }
 }
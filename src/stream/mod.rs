@@ -0,0 +1,209 @@
+//! Drives the worker threads that stream random data out to named pipes.
+//!
+//! The actual pipe I/O is behind the [`PipeServer`] trait so that this
+//! module's worker loop doesn't need to know whether it's talking to a
+//! Unix FIFO or a Windows named pipe; see `unix` and `windows` for the two
+//! backends.
+
+use crate::core::*;
+use crate::error::{ErrorKind, Result, WorkerError};
+use crate::rng::{CryptoRng, ReseedPolicy};
+use crate::workers::{OutputSpec, WorkerHandle, WorkerPool, WorkerSpec};
+
+use std::panic;
+use std::path::Path;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+use unix::{poll_sleep, UnixPipeServer as Pipe};
+#[cfg(windows)]
+use windows::{poll_sleep, WindowsPipeServer as Pipe};
+
+#[cfg(unix)]
+use crate::jobserver::JobServer;
+
+/// What happened when a [`PipeServer`] tried to write a block of data.
+pub enum WriteOutcome {
+    /// The write completed; the caller may write another block.
+    Written,
+    /// The write didn't complete yet; call `wait_writable` and retry.
+    WouldBlock,
+    /// The reader went away (Unix `BrokenPipe` / Windows `ERROR_NO_DATA`);
+    /// the caller should reopen the pipe.
+    Disconnected,
+}
+
+/// A platform's named-pipe / FIFO backend.
+pub trait PipeServer: Sized {
+    /// Try to open `path` for writing in non-blocking mode. Returns
+    /// `Ok(None)` if no reader has connected yet (Unix `ENXIO` / Windows
+    /// `ERROR_PIPE_LISTENING`), so the caller can back off and retry.
+    fn connect(path: &Path) -> Result<Option<Self>>;
+
+    /// Wait up to one `default_sleep_time()` tick for the pipe to become
+    /// ready to accept another write, returning `false` on timeout. Bounded
+    /// rather than blocking indefinitely, so that a caller looping on this
+    /// can recheck whether it should give up instead of a stalled reader
+    /// (one that stopped reading without closing the pipe) wedging the
+    /// thread forever.
+    fn wait_writable(&self) -> Result<bool>;
+
+    /// Write `buf` to the pipe.
+    fn write(&mut self, buf: &[u8]) -> Result<WriteOutcome>;
+}
+
+fn run_worker(mut spec: WorkerSpec) -> Result<()> {
+    let mut rng = CryptoRng::from_entropy_with_policy(spec.reseed_policy())?;
+
+    while spec.is_running() {
+        let mut pipe = match Pipe::connect(spec.path())? {
+            Some(pipe) => pipe,
+            None => {
+                // No clients have opened the pipe yet.
+                poll_sleep(default_sleep_time())?;
+                continue;
+            }
+        };
+
+        // Repeatedly write blocks of random data to the named pipe
+        while spec.is_running() {
+            let buf = rng.regenerate();
+            let n_bytes = buf.len();
+
+            match pipe.write(buf)? {
+                WriteOutcome::Written => {
+                    // Respect this path's throughput cap, if it has one.
+                    // Sleep in default_sleep_time()-bounded chunks rather
+                    // than the full wait in one call, rechecking is_running
+                    // between chunks, so a shutdown request isn't stuck
+                    // behind a long rate-limit wait.
+                    let mut remaining = spec.throttle(n_bytes);
+                    while spec.is_running() && remaining > Duration::ZERO {
+                        let chunk = remaining.min(default_sleep_time());
+                        poll_sleep(chunk)?;
+                        remaining -= chunk;
+                    }
+                }
+                WriteOutcome::WouldBlock => {
+                    while spec.is_running() && !pipe.wait_writable()? {}
+                }
+                // Pipe was closed by client
+                WriteOutcome::Disconnected => break,
+            }
+        }
+    }
+
+    // Perform rng.regenerate() one more time to erase the final
+    // key state
+    rng.regenerate();
+
+    Ok(())
+}
+
+pub fn run_workers(outputs: &[OutputSpec], n_workers: usize, reseed_policy: ReseedPolicy) -> Result<()> {
+    let pool = start_workers(outputs, n_workers, reseed_policy)?;
+    let r = pool.running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })
+    .expect("Error setting Ctrl-C handler");
+    join_workers(pool.handles)
+}
+
+pub fn join_workers(handles: Vec<WorkerHandle>) -> Result<()> {
+    let errors = handles
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, wh)| {
+            match wh.handle.join() {
+                Ok(res) => match res {
+                    Err(e) => Some(WorkerError::new(i, wh.path, e)),
+                    _ => None,
+                },
+                // In theory we should only reach this point if one of the
+                // threads panics
+                Err(e) => panic::resume_unwind(e),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorKind::WorkerErrors(errors))
+    }
+}
+
+pub fn start_workers(
+    outputs: &[OutputSpec],
+    n_workers: usize,
+    reseed_policy: ReseedPolicy,
+) -> Result<WorkerPool> {
+    let running = Arc::new(AtomicBool::new(true));
+
+    // If we're running underneath `make -jN` and it advertised a jobserver
+    // in MAKEFLAGS, bound the number of concurrently live writer threads to
+    // the number of slots it actually grants us rather than letting every
+    // worker write at once. All workers are still spawned up front; each
+    // one waits for its slot inside its own thread (see below). The
+    // jobserver protocol relies on
+    // inherited Unix file descriptors, so it has no Windows equivalent here.
+    #[cfg(unix)]
+    let jobserver = JobServer::from_env()?.map(Arc::new);
+
+    let mut handles = Vec::with_capacity(outputs.len() * n_workers);
+    let mut worker_id = 0usize;
+
+    // Each output gets its own pool of `n_workers` threads, sharing this
+    // one `WorkerPool` rather than the caller spawning one pool per path.
+    for output in outputs {
+        // A path's throughput cap covers all of its workers together, and
+        // each worker keeps its own token bucket, so each one gets an equal
+        // share of the cap (rounded up).
+        let n = n_workers.max(1) as u64;
+        let worker_output = OutputSpec {
+            path: output.path.clone(),
+            rate_limit: output.rate_limit.map(|rate| rate / n + u64::from(rate % n != 0)),
+        };
+        for _ in 0..n_workers {
+            let spec = WorkerSpec::new(&worker_output, &running, reseed_policy);
+            #[cfg(unix)]
+            let jobserver = jobserver.clone();
+
+            let path = output.path.clone();
+            let handle = thread::Builder::new()
+                .name(format!("worker {} ({})", worker_id, path.display()))
+                .spawn(move || {
+                    // Every jobserver participant owns one implicit slot
+                    // for free; each additional worker must acquire its
+                    // own. This happens here, inside the worker thread
+                    // itself, rather than in the spawn loop above, so that
+                    // a jobserver slow to free up slots blocks only this
+                    // thread — the spawn loop and the caller's Ctrl-C
+                    // handler are unaffected.
+                    #[cfg(unix)]
+                    let _token = match &jobserver {
+                        Some(js) if worker_id > 0 => js.clone().acquire(spec.running_flag())?,
+                        _ => None,
+                    };
+                    run_worker(spec)
+                })
+                .map_err(ErrorKind::IOError)?;
+
+            handles.push(WorkerHandle { path, handle });
+            worker_id += 1;
+        }
+    }
+
+    Ok(WorkerPool { running, handles })
+}
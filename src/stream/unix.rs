@@ -0,0 +1,102 @@
+//! Unix FIFO backend for [`PipeServer`].
+
+use super::{PipeServer, WriteOutcome};
+use crate::core::default_sleep_time;
+use crate::error::{ErrorKind, Result};
+
+use nix::errno::Errno;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::time::Duration;
+
+pub struct UnixPipeServer {
+    stream: BufWriter<fs::File>,
+    fd: RawFd,
+}
+
+impl PipeServer for UnixPipeServer {
+    fn connect(path: &Path) -> Result<Option<Self>> {
+        let file = fs::OpenOptions::new()
+            .read(false)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path);
+
+        match file {
+            Ok(file) => {
+                let fd = file.as_raw_fd();
+                Ok(Some(UnixPipeServer {
+                    stream: BufWriter::new(file),
+                    fd,
+                }))
+            }
+            // No clients have opened the pipe yet.
+            Err(e) if Some(libc::ENXIO) == e.raw_os_error() => Ok(None),
+            Err(e) => Err(os_error(e)),
+        }
+    }
+
+    fn wait_writable(&self) -> Result<bool> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        let timeout_ms = default_sleep_time().as_millis() as i32;
+
+        loop {
+            match unsafe { libc::poll(&mut pfd, 1, timeout_ms) } {
+                n if n < 0 => {
+                    let e = io::Error::last_os_error();
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(os_error(e));
+                    }
+                }
+                0 => return Ok(false),
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<WriteOutcome> {
+        match self.stream.write_all(buf) {
+            Ok(()) => Ok(WriteOutcome::Written),
+            Err(e) => match e.kind() {
+                // Pipe was closed by client
+                io::ErrorKind::BrokenPipe => Ok(WriteOutcome::Disconnected),
+                io::ErrorKind::WouldBlock => Ok(WriteOutcome::WouldBlock),
+                _ => Err(os_error(e)),
+            },
+        }
+    }
+}
+
+/// Sleep for `timeout` via `poll(2)` with no file descriptors, so that the
+/// only blocking primitive the worker loop (`run_worker`) uses is `poll`,
+/// rather than mixing in `thread::sleep`.
+pub(super) fn poll_sleep(timeout: Duration) -> Result<()> {
+    let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+    loop {
+        match unsafe { libc::poll(std::ptr::null_mut(), 0, timeout_ms) } {
+            n if n < 0 => {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(os_error(e));
+                }
+            }
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Map an `io::Error` carrying a raw errno into `ErrorKind::UnixError`,
+/// falling back to `ErrorKind::IOError` for errors that don't carry one.
+fn os_error(e: io::Error) -> ErrorKind {
+    match e.raw_os_error() {
+        Some(code) => ErrorKind::UnixError(Errno::from_i32(code)),
+        None => ErrorKind::IOError(e),
+    }
+}
@@ -0,0 +1,253 @@
+//! Windows named-pipe backend for [`PipeServer`].
+//!
+//! Mirrors the Unix FIFO backend in `super::unix`: `CreateNamedPipeW` +
+//! `ConnectNamedPipe` stand in for `open`ing a FIFO, and overlapped
+//! (asynchronous) I/O stands in for `O_NONBLOCK`, so a single writer
+//! thread can detect "no reader yet" without blocking on it.
+
+use super::{PipeServer, WriteOutcome};
+use crate::core::default_sleep_time;
+use crate::error::{ErrorKind, Result};
+
+use std::ffi::c_void;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+use std::time::Duration;
+
+type Handle = *mut c_void;
+
+const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+const PIPE_ACCESS_OUTBOUND: u32 = 0x0000_0002;
+const FILE_FLAG_OVERLAPPED: u32 = 0x4000_0000;
+const PIPE_TYPE_BYTE: u32 = 0x0000_0000;
+const PIPE_WAIT: u32 = 0x0000_0000;
+const PIPE_UNLIMITED_INSTANCES: u32 = 255;
+const DEFAULT_BUFFER_SIZE: u32 = 1024;
+
+const ERROR_PIPE_CONNECTED: u32 = 535;
+const ERROR_PIPE_LISTENING: u32 = 536;
+const ERROR_IO_PENDING: u32 = 997;
+const ERROR_IO_INCOMPLETE: u32 = 996;
+const ERROR_NO_DATA: u32 = 232;
+const ERROR_BROKEN_PIPE: u32 = 109;
+const WAIT_TIMEOUT: u32 = 258;
+
+#[repr(C)]
+struct Overlapped {
+    internal: usize,
+    internal_high: usize,
+    offset: u32,
+    offset_high: u32,
+    h_event: Handle,
+}
+
+#[allow(non_snake_case)]
+extern "system" {
+    fn CreateNamedPipeW(
+        lpName: *const u16,
+        dwOpenMode: u32,
+        dwPipeMode: u32,
+        nMaxInstances: u32,
+        nOutBufferSize: u32,
+        nInBufferSize: u32,
+        nDefaultTimeOut: u32,
+        lpSecurityAttributes: *mut c_void,
+    ) -> Handle;
+    fn ConnectNamedPipe(hNamedPipe: Handle, lpOverlapped: *mut Overlapped) -> i32;
+    fn WriteFile(
+        hFile: Handle,
+        lpBuffer: *const u8,
+        nNumberOfBytesToWrite: u32,
+        lpNumberOfBytesWritten: *mut u32,
+        lpOverlapped: *mut Overlapped,
+    ) -> i32;
+    fn GetOverlappedResult(
+        hFile: Handle,
+        lpOverlapped: *mut Overlapped,
+        lpNumberOfBytesTransferred: *mut u32,
+        bWait: i32,
+    ) -> i32;
+    fn CreateEventW(
+        lpEventAttributes: *mut c_void,
+        bManualReset: i32,
+        bInitialState: i32,
+        lpName: *const u16,
+    ) -> Handle;
+    fn WaitForSingleObject(hHandle: Handle, dwMilliseconds: u32) -> u32;
+    fn ResetEvent(hEvent: Handle) -> i32;
+    fn CancelIoEx(hFile: Handle, lpOverlapped: *mut Overlapped) -> i32;
+    fn CloseHandle(hObject: Handle) -> i32;
+    fn GetLastError() -> u32;
+}
+
+fn to_wide(path: &Path) -> Vec<u16> {
+    path.as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect()
+}
+
+fn win_error() -> ErrorKind {
+    ErrorKind::WindowsError(unsafe { GetLastError() })
+}
+
+pub struct WindowsPipeServer {
+    handle: Handle,
+    event: Handle,
+    overlapped: Box<Overlapped>,
+}
+
+// The handles here are only ever touched from the worker thread that owns
+// this value; we never share a `WindowsPipeServer` across threads.
+unsafe impl Send for WindowsPipeServer {}
+
+impl PipeServer for WindowsPipeServer {
+    fn connect(path: &Path) -> Result<Option<Self>> {
+        let wide_name = to_wide(path);
+
+        let handle = unsafe {
+            CreateNamedPipeW(
+                wide_name.as_ptr(),
+                PIPE_ACCESS_OUTBOUND | FILE_FLAG_OVERLAPPED,
+                PIPE_TYPE_BYTE | PIPE_WAIT,
+                PIPE_UNLIMITED_INSTANCES,
+                DEFAULT_BUFFER_SIZE,
+                0,
+                0,
+                ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(win_error());
+        }
+
+        let event = unsafe { CreateEventW(ptr::null_mut(), 1, 0, ptr::null()) };
+        if event.is_null() {
+            let e = win_error();
+            unsafe { CloseHandle(handle) };
+            return Err(e);
+        }
+
+        let mut overlapped = Box::new(Overlapped {
+            internal: 0,
+            internal_high: 0,
+            offset: 0,
+            offset_high: 0,
+            h_event: event,
+        });
+
+        if unsafe { ConnectNamedPipe(handle, overlapped.as_mut()) } != 0 {
+            // A reader connected synchronously.
+            return Ok(Some(WindowsPipeServer {
+                handle,
+                event,
+                overlapped,
+            }));
+        }
+
+        match unsafe { GetLastError() } {
+            // A reader beat us to it between CreateNamedPipeW and
+            // ConnectNamedPipe.
+            ERROR_PIPE_CONNECTED => Ok(Some(WindowsPipeServer {
+                handle,
+                event,
+                overlapped,
+            })),
+            // No reader yet; this is the Windows equivalent of Unix's
+            // ENXIO, so the caller backs off and retries.
+            ERROR_PIPE_LISTENING | ERROR_IO_PENDING => {
+                unsafe {
+                    CloseHandle(event);
+                    CloseHandle(handle);
+                }
+                Ok(None)
+            }
+            code => {
+                unsafe {
+                    CloseHandle(event);
+                    CloseHandle(handle);
+                }
+                Err(ErrorKind::WindowsError(code))
+            }
+        }
+    }
+
+    fn wait_writable(&self) -> Result<bool> {
+        let timeout_ms = default_sleep_time().as_millis() as u32;
+        match unsafe { WaitForSingleObject(self.event, timeout_ms) } {
+            0 => Ok(true),
+            WAIT_TIMEOUT => Ok(false),
+            _ => Err(win_error()),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<WriteOutcome> {
+        // The manual-reset event on `self.overlapped` stays signaled once a
+        // prior write completes, so it must be reset before starting a new
+        // overlapped operation or `GetOverlappedResult` below could return
+        // immediately against that stale completion instead of this one.
+        if unsafe { ResetEvent(self.event) } == 0 {
+            return Err(win_error());
+        }
+
+        let mut written = 0u32;
+        let ok = unsafe {
+            WriteFile(
+                self.handle,
+                buf.as_ptr(),
+                buf.len() as u32,
+                &mut written,
+                self.overlapped.as_mut(),
+            )
+        };
+        if ok != 0 {
+            return Ok(WriteOutcome::Written);
+        }
+
+        match unsafe { GetLastError() } {
+            // The write is in flight. Poll rather than blocking here, so a
+            // slow reader can't wedge this thread past what wait_writable
+            // would otherwise bound it to; the caller is expected to retry
+            // via wait_writable, same as the Unix backend's WouldBlock.
+            ERROR_IO_PENDING => {
+                let mut transferred = 0u32;
+                let ok = unsafe {
+                    GetOverlappedResult(self.handle, self.overlapped.as_mut(), &mut transferred, 0)
+                };
+                if ok != 0 {
+                    Ok(WriteOutcome::Written)
+                } else {
+                    match unsafe { GetLastError() } {
+                        ERROR_IO_INCOMPLETE => Ok(WriteOutcome::WouldBlock),
+                        ERROR_NO_DATA | ERROR_BROKEN_PIPE => Ok(WriteOutcome::Disconnected),
+                        code => Err(ErrorKind::WindowsError(code)),
+                    }
+                }
+            }
+            ERROR_NO_DATA | ERROR_BROKEN_PIPE => Ok(WriteOutcome::Disconnected),
+            code => Err(ErrorKind::WindowsError(code)),
+        }
+    }
+}
+
+impl Drop for WindowsPipeServer {
+    fn drop(&mut self) {
+        unsafe {
+            // write() may return before an overlapped write it started has
+            // actually completed (WriteOutcome::WouldBlock); cancel it so
+            // the kernel doesn't keep a reference to `self.overlapped`
+            // after this `Box` is freed.
+            CancelIoEx(self.handle, ptr::null_mut());
+            CloseHandle(self.event);
+            CloseHandle(self.handle);
+        }
+    }
+}
+
+/// Sleep for `timeout`. Unlike the Unix backend, there's no `poll(2)`
+/// equivalent in play here, so this is a plain OS sleep.
+pub(super) fn poll_sleep(timeout: Duration) -> Result<()> {
+    std::thread::sleep(timeout);
+    Ok(())
+}
@@ -1,15 +1,95 @@
 use crate::error::Result;
+use crate::rng::ReseedPolicy;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// One `-o/--output` argument: the path of a FIFO to serve, and an
+/// optional throughput cap in bytes/sec, written as `PATH@RATE`.
+#[derive(Debug, Clone)]
+pub struct OutputSpec {
+    pub path: PathBuf,
+    pub rate_limit: Option<u64>,
+}
+
+impl FromStr for OutputSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once('@') {
+            Some((path, rate)) => {
+                let rate_limit = rate
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid rate limit `{}` in `{}`; expected bytes/sec", rate, s))?;
+                Ok(OutputSpec {
+                    path: PathBuf::from(path),
+                    rate_limit: Some(rate_limit),
+                })
+            }
+            None => Ok(OutputSpec {
+                path: PathBuf::from(s),
+                rate_limit: None,
+            }),
+        }
+    }
+}
+
+/// A simple token bucket used to cap a worker's throughput to a configured
+/// rate, so that one output path can't starve the others when they share a
+/// worker pool.
+#[derive(Debug)]
+struct RateLimiter {
+    bytes_per_sec: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_sec: u64) -> Self {
+        RateLimiter {
+            bytes_per_sec,
+            tokens: bytes_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Record that `n_bytes` were just written, refilling the bucket based
+    /// on elapsed time, and return how long the caller should wait before
+    /// writing again to stay within the configured rate.
+    fn consume(&mut self, n_bytes: usize) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+
+        let capacity = self.bytes_per_sec as f64;
+        self.tokens = (self.tokens + elapsed * capacity).min(capacity);
+        self.tokens -= n_bytes as f64;
+
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / capacity)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct WorkerPool {
     pub running: Arc<AtomicBool>,
-    pub handles: Vec<JoinHandle<Result<()>>>,
+    pub handles: Vec<WorkerHandle>,
+}
+
+/// A spawned worker thread together with the output path it's serving, so
+/// that failures can be attributed to a path as well as a thread id.
+#[derive(Debug)]
+pub struct WorkerHandle {
+    pub path: PathBuf,
+    pub handle: JoinHandle<Result<()>>,
 }
 
 /// Struct specifying the parameters and work that a given
@@ -22,14 +102,24 @@ pub struct WorkerSpec {
     /// `AtomicBool` that can be checked to determine whether
     /// or not the worker should continue working.
     running: Arc<AtomicBool>,
+
+    /// Policy governing how often the worker's `CryptoRng` mixes in fresh
+    /// OS entropy.
+    reseed_policy: ReseedPolicy,
+
+    /// This worker's share of its path's throughput cap, if one was
+    /// configured for this output.
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl WorkerSpec {
-    /// Create a new `WorkerSpec` instance.
-    pub fn new(path: &Path, running: &Arc<AtomicBool>) -> Self {
+    /// Create a new `WorkerSpec` instance for the given output.
+    pub fn new(output: &OutputSpec, running: &Arc<AtomicBool>, reseed_policy: ReseedPolicy) -> Self {
         WorkerSpec {
-            pathbuf: path.to_owned(),
+            pathbuf: output.path.clone(),
             running: running.clone(),
+            reseed_policy,
+            rate_limiter: output.rate_limit.map(RateLimiter::new),
         }
     }
 
@@ -42,4 +132,71 @@ impl WorkerSpec {
     pub fn path(&self) -> &Path {
         self.pathbuf.as_path()
     }
+
+    /// Return the `AtomicBool` backing `is_running`, for code that needs to
+    /// poll it outside of the worker itself (e.g. while waiting on a
+    /// jobserver slot).
+    pub fn running_flag(&self) -> &Arc<AtomicBool> {
+        &self.running
+    }
+
+    /// Return the policy the worker's `CryptoRng` should use for mixing in
+    /// fresh OS entropy.
+    pub fn reseed_policy(&self) -> ReseedPolicy {
+        self.reseed_policy
+    }
+
+    /// Account for `n_bytes` just having been written, returning how long
+    /// the worker should wait before writing again to respect its share of
+    /// this path's throughput cap. Returns `Duration::ZERO` if no cap was
+    /// configured.
+    pub fn throttle(&mut self, n_bytes: usize) -> Duration {
+        match &mut self.rate_limiter {
+            Some(limiter) => limiter.consume(n_bytes),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_output_spec_parse_plain_path() {
+        let spec: OutputSpec = "/tmp/fifo".parse().unwrap();
+        assert_eq!(spec.path, PathBuf::from("/tmp/fifo"));
+        assert_eq!(spec.rate_limit, None);
+    }
+
+    #[test]
+    fn test_output_spec_parse_with_rate() {
+        let spec: OutputSpec = "/tmp/fifo@1024".parse().unwrap();
+        assert_eq!(spec.path, PathBuf::from("/tmp/fifo"));
+        assert_eq!(spec.rate_limit, Some(1024));
+    }
+
+    #[test]
+    fn test_output_spec_parse_invalid_rate() {
+        assert!("/tmp/fifo@nan".parse::<OutputSpec>().is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_consume_within_budget() {
+        let mut limiter = RateLimiter::new(1000);
+        // The bucket starts full, so consuming less than the full budget
+        // shouldn't require any wait.
+        assert_eq!(limiter.consume(500), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rate_limiter_consume_over_budget() {
+        let mut limiter = RateLimiter::new(1000);
+        limiter.consume(1000);
+
+        // The bucket is now empty; consuming again immediately must wait
+        // roughly n_bytes / bytes_per_sec before the caller may write again.
+        let wait = limiter.consume(1000);
+        assert!(wait > Duration::ZERO);
+    }
 }